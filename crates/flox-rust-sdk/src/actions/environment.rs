@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::{fs, io};
 
-use log::{info, warn};
+use log::{debug, error, info, warn};
 use runix::arguments::eval::EvaluationArgs;
 use runix::arguments::NixArgs;
 use runix::command::Build;
@@ -16,6 +16,8 @@ use crate::utils::errors::IoError;
 
 static FLOX_NIX: &str = "flox.nix";
 static CATALOG_JSON: &str = "catalog.json";
+/// The link created by `nix build` in the current directory
+static RESULT_LINK: &str = "./result";
 
 pub struct Environment<'flox> {
     flox: &'flox Flox,
@@ -33,6 +35,23 @@ struct BuiltEnvironment {
     result: PathBuf,
 }
 
+/// A pending modification of the files backing an environment
+///
+/// Files and build links are snapshotted right before they are modified.
+/// Unless [EnvironmentTransaction::commit] is called,
+/// dropping the transaction restores the snapshot,
+/// so that a failed build or write can not leave `flox.nix`, `catalog.json`
+/// and the build link out of sync.
+/// Files that were not snapshotted yet are left untouched.
+#[derive(Default)]
+pub struct EnvironmentTransaction {
+    /// Original contents of each tracked file, [None] if the file did not exist
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// Original target of each tracked link, [None] if the link did not exist
+    links: Vec<(PathBuf, Option<PathBuf>)>,
+    committed: bool,
+}
+
 /////////
 // Errors
 /////////
@@ -178,8 +197,19 @@ impl<'flox> Environment<'flox> {
         )?;
 
         if n_new > 0 {
+            let mut transaction = EnvironmentTransaction::default();
+            transaction
+                .snapshot_links([PathBuf::from(RESULT_LINK)])
+                .map_err(EnvironmentError::from)?;
             let built_environment = self.build(&edited).await?;
+
+            // snapshot only after the build,
+            // edits made to the environment while building are kept if it fails
+            transaction
+                .snapshot_files([self.flox_nix.clone(), self.catalog_json.clone()])
+                .map_err(EnvironmentError::from)?;
             self.write_environment(&edited, &built_environment)?;
+            transaction.commit();
         }
 
         match n_new {
@@ -205,6 +235,7 @@ impl<'flox> Environment<'flox> {
     /////////////////
     // Helper methods
     /////////////////
+
     async fn read_flox_nix(&self) -> Result<String, EnvironmentError> {
         let file_contents = tokio::fs::read_to_string(&self.flox_nix)
             .await
@@ -285,7 +316,7 @@ impl<'flox> Environment<'flox> {
         // TODO as far as I can tell the above never fails
         Ok(BuiltEnvironment {
             // TODO use --out-link
-            result: PathBuf::from("./result"),
+            result: PathBuf::from(RESULT_LINK),
         })
     }
 
@@ -308,10 +339,115 @@ impl<'flox> Environment<'flox> {
     }
 }
 
+//////////////////////////////
+// impl EnvironmentTransaction
+//////////////////////////////
+impl EnvironmentTransaction {
+    /// Snapshot the contents of `files` before modifying them
+    pub fn snapshot_files(
+        &mut self,
+        files: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<(), IoError> {
+        for file in files {
+            match fs::read(&file) {
+                Ok(contents) => self.files.push((file, Some(contents))),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => self.files.push((file, None)),
+                Err(err) => return Err(IoError::Read { file, err }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Snapshot the targets of the symlinks `links` before replacing them
+    pub fn snapshot_links(
+        &mut self,
+        links: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<(), IoError> {
+        for link in links {
+            match fs::read_link(&link) {
+                Ok(target) => self.links.push((link, Some(target))),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => self.links.push((link, None)),
+                Err(err) => return Err(IoError::Read { file: link, err }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep all modifications made since the transaction was started
+    pub fn commit(mut self) {
+        self.committed = true;
+        debug!("Committed environment modification");
+    }
+
+    /// Restore all files and links to their snapshotted state
+    ///
+    /// Files and links that did not exist when they were snapshotted are removed.
+    /// Returns whether anything differed from the snapshot.
+    fn restore(&self) -> Result<bool, IoError> {
+        let mut restored = false;
+
+        for (link, target) in &self.links {
+            if fs::read_link(link).ok() == *target {
+                continue;
+            }
+            restored = true;
+            remove_if_exists(link)?;
+            if let Some(target) = target {
+                std::os::unix::fs::symlink(target, link).map_err(|err| IoError::Write {
+                    file: link.clone(),
+                    err,
+                })?;
+            }
+        }
+
+        for (file, contents) in &self.files {
+            if fs::read(file).ok() == *contents {
+                continue;
+            }
+            restored = true;
+            match contents {
+                Some(contents) => fs::write(file, contents).map_err(|err| IoError::Write {
+                    file: file.clone(),
+                    err,
+                })?,
+                None => remove_if_exists(file)?,
+            }
+        }
+        Ok(restored)
+    }
+}
+
+impl Drop for EnvironmentTransaction {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        match self.restore() {
+            Ok(true) => warn!("Environment modification failed, all changes were rolled back"),
+            Ok(false) => debug!("Environment modification failed before making any changes"),
+            Err(err) => {
+                error!("Environment modification failed and could not be rolled back: {err}")
+            },
+        }
+    }
+}
+
 ///////////////////
 // Helper functions
 ///////////////////
 
+/// Remove a file or symlink, ignoring that it may not exist
+fn remove_if_exists(file: &PathBuf) -> Result<(), IoError> {
+    match fs::remove_file(file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(IoError::Remove {
+            file: file.clone(),
+            err,
+        }),
+        _ => Ok(()),
+    }
+}
+
 /// Using fs::copy copies permissions from the Nix store, which we don't want, so open (or
 /// create) the files and copy with io::copy
 fn copy_file_without_permissions(from: &PathBuf, to: &PathBuf) -> Result<(), EnvironmentError> {
@@ -334,3 +470,84 @@ fn copy_file_without_permissions(from: &PathBuf, to: &PathBuf) -> Result<(), Env
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transaction_restores_on_drop() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let existing = tempdir.path().join(FLOX_NIX);
+        let created = tempdir.path().join(CATALOG_JSON);
+        fs::write(&existing, "original").unwrap();
+
+        let mut transaction = EnvironmentTransaction::default();
+        transaction
+            .snapshot_files([existing.clone(), created.clone()])
+            .unwrap();
+        fs::write(&existing, "modified").unwrap();
+        fs::write(&created, "new").unwrap();
+        drop(transaction);
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "original");
+        assert!(!created.exists());
+    }
+
+    #[test]
+    fn transaction_keeps_changes_on_commit() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let existing = tempdir.path().join(FLOX_NIX);
+        fs::write(&existing, "original").unwrap();
+
+        let mut transaction = EnvironmentTransaction::default();
+        transaction.snapshot_files([existing.clone()]).unwrap();
+        fs::write(&existing, "modified").unwrap();
+        transaction.commit();
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "modified");
+    }
+
+    #[test]
+    fn transaction_restores_links() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let link = tempdir.path().join("result");
+        let unsnapshotted = tempdir.path().join(FLOX_NIX);
+        std::os::unix::fs::symlink("/nix/store/old", &link).unwrap();
+        fs::write(&unsnapshotted, "original").unwrap();
+
+        let mut transaction = EnvironmentTransaction::default();
+        transaction.snapshot_links([link.clone()]).unwrap();
+        fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink("/nix/store/new", &link).unwrap();
+        fs::write(&unsnapshotted, "edited by the user").unwrap();
+        drop(transaction);
+
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            PathBuf::from("/nix/store/old")
+        );
+        assert_eq!(
+            fs::read_to_string(&unsnapshotted).unwrap(),
+            "edited by the user"
+        );
+    }
+
+    #[test]
+    fn transaction_reports_unchanged_snapshot() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let existing = tempdir.path().join(FLOX_NIX);
+        let link = tempdir.path().join("result");
+        fs::write(&existing, "original").unwrap();
+
+        let mut transaction = EnvironmentTransaction::default();
+        transaction.snapshot_files([existing.clone()]).unwrap();
+        transaction.snapshot_links([link.clone()]).unwrap();
+        assert!(!transaction.restore().unwrap());
+
+        fs::write(&existing, "modified").unwrap();
+        assert!(transaction.restore().unwrap());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "original");
+        transaction.commit();
+    }
+}
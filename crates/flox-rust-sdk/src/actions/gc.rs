@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::debug;
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use thiserror::Error;
+
+use crate::flox::Flox;
+use crate::utils::errors::IoError;
+
+/// Name of the directory in `cache_dir` holding the temporary directories
+/// of individual flox processes
+pub const PROCESS_DIR_NAME: &str = "process";
+
+/// File in a process' temporary directory recording the PID of its owner
+pub const PROCESS_DIR_OWNER_FILE: &str = "owner";
+
+/// Temporary directories untouched for longer than this are considered abandoned,
+/// unless their owner is still running
+///
+/// Processes usually clean up after themselves,
+/// but directories are kept in debug mode and leak if flox is killed.
+pub const PROCESS_DIR_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Error, Debug)]
+pub enum GcError {
+    #[error(transparent)]
+    Io(#[from] IoError),
+}
+
+/// Removes state flox leaves behind in its cache directory
+pub struct GarbageCollection<'flox> {
+    flox: &'flox Flox,
+    dry_run: bool,
+}
+
+impl<'flox> GarbageCollection<'flox> {
+    /// Collect garbage of `flox`, only reporting what would be removed if `dry_run` is set
+    pub fn new(flox: &'flox Flox, dry_run: bool) -> Self {
+        Self { flox, dry_run }
+    }
+
+    /// Remove abandoned temporary directories of earlier flox processes
+    ///
+    /// Returns the removed directories (or those that would be removed in a dry run).
+    /// The temporary directory of the running process is never removed.
+    pub fn process_dirs(&self) -> Result<Vec<PathBuf>, GcError> {
+        collect_process_dirs(
+            &self.flox.cache_dir.join(PROCESS_DIR_NAME),
+            &self.flox.temp_dir,
+            PROCESS_DIR_MAX_AGE,
+            self.dry_run,
+        )
+    }
+}
+
+/// Record the current process as owner of its temporary directory `dir`
+///
+/// Directories of running owners are never garbage collected.
+pub fn record_process_dir_owner(dir: &Path) -> Result<(), IoError> {
+    let owner_file = dir.join(PROCESS_DIR_OWNER_FILE);
+    fs::write(&owner_file, std::process::id().to_string()).map_err(|err| IoError::Write {
+        file: owner_file,
+        err,
+    })
+}
+
+/// Whether the process recorded as owner of `dir` is still running
+///
+/// Directories without a recorded owner are considered abandoned.
+fn owner_is_running(dir: &Path) -> bool {
+    fs::read_to_string(dir.join(PROCESS_DIR_OWNER_FILE))
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+        .map_or(false, |pid| {
+            !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH))
+        })
+}
+
+fn collect_process_dirs(
+    process_dir: &Path,
+    keep: &Path,
+    max_age: Duration,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>, GcError> {
+    let entries = match fs::read_dir(process_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => Err(IoError::Read {
+            file: process_dir.to_path_buf(),
+            err,
+        })?,
+    };
+
+    let now = SystemTime::now();
+    let mut collected = Vec::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|err| IoError::Read {
+            file: process_dir.to_path_buf(),
+            err,
+        })?;
+        let path = entry.path();
+
+        if path == keep {
+            continue;
+        }
+
+        let modified = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| IoError::Read {
+                file: path.clone(),
+                err,
+            })?;
+
+        // modification times in the future count as fresh
+        let age = now.duration_since(modified).unwrap_or_default();
+        if age < max_age {
+            continue;
+        }
+
+        if owner_is_running(&path) {
+            debug!("Keeping {path:?}, its owner is still running");
+            continue;
+        }
+
+        if dry_run {
+            debug!("Would remove {path:?}");
+        } else {
+            debug!("Removing {path:?}");
+            let removed = if matches!(entry.file_type(), Ok(ty) if ty.is_dir()) {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            removed.map_err(|err| IoError::Remove {
+                file: path.clone(),
+                err,
+            })?;
+        }
+
+        collected.push(path);
+    }
+
+    collected.sort();
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_process_dirs_keeps_current() {
+        let process_dir = tempfile::tempdir().unwrap();
+        let current = process_dir.path().join("current");
+        let stale = process_dir.path().join("stale");
+        fs::create_dir(&current).unwrap();
+        fs::create_dir(&stale).unwrap();
+
+        let collected =
+            collect_process_dirs(process_dir.path(), &current, Duration::ZERO, true).unwrap();
+        assert_eq!(collected, vec![stale.clone()]);
+        assert!(stale.exists());
+
+        let collected =
+            collect_process_dirs(process_dir.path(), &current, Duration::ZERO, false).unwrap();
+        assert_eq!(collected, vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(current.exists());
+    }
+
+    #[test]
+    fn collect_process_dirs_keeps_running_owners() {
+        let process_dir = tempfile::tempdir().unwrap();
+        let running = process_dir.path().join("running");
+        let exited = process_dir.path().join("exited");
+        fs::create_dir(&running).unwrap();
+        fs::create_dir(&exited).unwrap();
+
+        record_process_dir_owner(&running).unwrap();
+        // PIDs are much smaller than i32::MAX in practice
+        fs::write(exited.join(PROCESS_DIR_OWNER_FILE), i32::MAX.to_string()).unwrap();
+
+        let collected = collect_process_dirs(
+            process_dir.path(),
+            &process_dir.path().join("current"),
+            Duration::ZERO,
+            false,
+        )
+        .unwrap();
+        assert_eq!(collected, vec![exited]);
+        assert!(running.exists());
+    }
+}
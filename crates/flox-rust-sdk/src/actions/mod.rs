@@ -1,2 +1,3 @@
 pub mod environment;
+pub mod gc;
 pub mod package;
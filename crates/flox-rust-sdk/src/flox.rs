@@ -57,6 +57,9 @@ pub struct Flox {
     pub access_tokens: Vec<(String, String)>,
     pub netrc_file: PathBuf,
 
+    /// additional substituters and their public keys injected in nix.conf
+    pub substituters: Vec<(String, String)>,
+
     pub channels: ChannelRegistry,
 
     pub system: String,
@@ -355,6 +358,28 @@ impl Flox {
             .collect())
     }
 
+    /// `nix.conf` settings adding [Flox::substituters]
+    ///
+    /// For nix processes not started through [Flox::nix], e.g. by flox-bash,
+    /// to be appended to `NIX_CONFIG`.
+    pub fn substituters_nix_config(&self) -> Option<String> {
+        if self.substituters.is_empty() {
+            return None;
+        }
+
+        let (urls, keys): (Vec<&str>, Vec<&str>) = self
+            .substituters
+            .iter()
+            .map(|(url, key)| (url.as_str(), key.as_str()))
+            .unzip();
+
+        Some(format!(
+            "extra-substituters = {}\nextra-trusted-public-keys = {}",
+            urls.join(" "),
+            keys.join(" ")
+        ))
+    }
+
     /// Produce a new Nix Backend
     ///
     /// This method performs backend independen configuration of nix
@@ -399,15 +424,17 @@ impl Flox {
                     .map(String::from)
                     .to_vec()
                     .into(),
-                extra_substituters: ["https://cache.floxdev.com"]
-                    .map(String::from)
-                    .to_vec()
+                extra_substituters: ["https://cache.floxdev.com".to_string()]
+                    .into_iter()
+                    .chain(self.substituters.iter().map(|(url, _)| url.clone()))
+                    .collect::<Vec<_>>()
                     .into(),
                 extra_trusted_public_keys: [
-                    "flox-store-public-0:8c/B+kjIaQ+BloCmNkRUKwaVPFWkriSAd0JJvuDu4F0=",
+                    "flox-store-public-0:8c/B+kjIaQ+BloCmNkRUKwaVPFWkriSAd0JJvuDu4F0=".to_string(),
                 ]
-                .map(String::from)
-                .to_vec()
+                .into_iter()
+                .chain(self.substituters.iter().map(|(_, key)| key.clone()))
+                .collect::<Vec<_>>()
                 .into(),
                 extra_access_tokens: self.access_tokens.clone().into(),
                 flake_registry: Some(global_registry_file.into()),
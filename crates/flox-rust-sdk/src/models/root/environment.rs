@@ -37,9 +37,9 @@ pub struct Metadata {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct GenerationMetadata {
-    created: u64,
-    last_active: u64,
-    log_message: Vec<String>,
+    pub created: u64,
+    pub last_active: u64,
+    pub log_message: Vec<String>,
     path: PathBuf,
     #[serde(default)]
     version: u32,
@@ -73,11 +73,74 @@ pub struct ElementSource {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Generation {
-    name: String,
-    metadata: GenerationMetadata,
+    pub name: String,
+    pub metadata: GenerationMetadata,
     elements: Vec<Element>,
 }
 
+/// Packages that differ between two generations
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct GenerationDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Packages in both generations that resolve to a different version
+    pub changed: Vec<String>,
+}
+
+impl Element {
+    /// A name identifying the package of this element
+    ///
+    /// `<flake>#<attr_path>` for flake packages, the store paths otherwise
+    pub fn package(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{}#{}", source.original_url, source.attr_path),
+            None => self.store_paths.join(" "),
+        }
+    }
+}
+
+impl Generation {
+    /// Compare the active packages of this generation with those of `newer`
+    ///
+    /// Packages are matched by [Element::package],
+    /// and reported as changed if their locked flake or store paths differ.
+    pub fn diff(&self, newer: &Generation) -> GenerationDiff {
+        let packages =
+            |generation: &Generation| -> BTreeMap<String, (Option<String>, Vec<String>)> {
+                generation
+                    .elements
+                    .iter()
+                    .filter(|element| element.active)
+                    .map(|element| {
+                        let locked_url = element.source.as_ref().map(|source| source.url.clone());
+                        (element.package(), (locked_url, element.store_paths.clone()))
+                    })
+                    .collect()
+            };
+
+        let old = packages(self);
+        let new = packages(newer);
+
+        GenerationDiff {
+            added: new
+                .keys()
+                .filter(|package| !old.contains_key(*package))
+                .cloned()
+                .collect(),
+            removed: old
+                .keys()
+                .filter(|package| !new.contains_key(*package))
+                .cloned()
+                .collect(),
+            changed: new
+                .iter()
+                .filter(|(package, locked)| old.get(*package).map_or(false, |old| old != *locked))
+                .map(|(package, _)| package.clone())
+                .collect(),
+        }
+    }
+}
+
 /// Implementations for an opened floxmeta
 impl<Git: GitProvider> Floxmeta<'_, Git> {
     pub async fn environment(
@@ -92,12 +155,24 @@ impl<Git: GitProvider> Floxmeta<'_, Git> {
     }
 
     /// Detect all environments from a floxmeta repo
+    ///
+    /// Fetches the remote state first, see [Self::local_environments]
+    /// to list environments without network access.
     pub async fn environments(&self) -> Result<Vec<Environment<Git>>, GetEnvironmentsError<Git>> {
         self.git
             .fetch()
             .await
             .map_err(GetEnvironmentsError::FetchBranches)?;
 
+        self.local_environments().await
+    }
+
+    /// Detect all environments from the last synced state of a floxmeta repo
+    ///
+    /// Remote environments are reported as of the last successful fetch.
+    pub async fn local_environments(
+        &self,
+    ) -> Result<Vec<Environment<Git>>, GetEnvironmentsError<Git>> {
         // get output of `git branch -av`
         let list_branches_output = self
             .git
@@ -189,6 +264,14 @@ impl<Git: GitProvider> Floxmeta<'_, Git> {
 
 /// Implementations for an environment
 impl<Git: GitProvider> Environment<'_, Git> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn system(&self) -> &str {
+        &self.system
+    }
+
     pub async fn metadata(&self) -> Result<Metadata, MetadataError<Git>> {
         let git = &self.floxmeta.git;
         let metadata_str = git
@@ -205,13 +288,43 @@ impl<Git: GitProvider> Environment<'_, Git> {
         Ok(metadata)
     }
 
+    /// All generations of the environment, ordered by generation number
+    pub async fn generations(&self) -> Result<Vec<Generation>, GenerationError<Git>> {
+        let metadata = self.metadata().await?;
+
+        let mut entries = metadata.generations.into_iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(name, _)| (name.parse::<u64>().ok(), name.clone()));
+
+        let mut generations = Vec::with_capacity(entries.len());
+        for (name, generation_metadata) in entries {
+            let manifest = self.manifest(&name).await?;
+            generations.push(Generation {
+                name,
+                metadata: generation_metadata,
+                elements: manifest.elements,
+            });
+        }
+
+        Ok(generations)
+    }
+
     pub async fn generation(&self, generation: &str) -> Result<Generation, GenerationError<Git>> {
-        let git = &self.floxmeta.git;
         let mut metadata = self.metadata().await?;
         let generation_metadata = metadata
             .generations
             .remove(generation)
             .ok_or(GenerationError::NotFound)?;
+        let manifest = self.manifest(generation).await?;
+
+        Ok(Generation {
+            name: generation.to_owned(),
+            metadata: generation_metadata,
+            elements: manifest.elements,
+        })
+    }
+
+    async fn manifest(&self, generation: &str) -> Result<Manifest, ManifestError<Git>> {
+        let git = &self.floxmeta.git;
         let manifest_content = git
             .show(&format!(
                 "{}.{}:{}/{}",
@@ -220,14 +333,8 @@ impl<Git: GitProvider> Environment<'_, Git> {
             .await
             .map_err(ManifestError::RetrieveManifest)?;
 
-        let manifest: Manifest = serde_json::from_str(&manifest_content.to_string_lossy())
-            .map_err(ManifestError::ParseManifest)?;
-
-        Ok(Generation {
-            name: generation.to_owned(),
-            metadata: generation_metadata,
-            elements: manifest.elements,
-        })
+        serde_json::from_str(&manifest_content.to_string_lossy())
+            .map_err(ManifestError::ParseManifest)
     }
 }
 
@@ -278,3 +385,87 @@ pub enum ManifestError<Git: GitProvider> {
     #[error("Failed parsing 'manifest.json': {0}")]
     ParseManifest(serde_json::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flake_element(attr_path: &str, active: bool) -> Element {
+        Element {
+            active,
+            store_paths: vec![format!("/nix/store/hash-{attr_path}")],
+            priority: None,
+            source: Some(ElementSource {
+                attr_path: attr_path.to_string(),
+                outputs: None,
+                url: "github:flox/nixpkgs/locked".to_string(),
+                original_url: "github:flox/nixpkgs".to_string(),
+            }),
+        }
+    }
+
+    fn upgraded_flake_element(attr_path: &str) -> Element {
+        Element {
+            store_paths: vec![format!("/nix/store/newhash-{attr_path}")],
+            source: Some(ElementSource {
+                url: "github:flox/nixpkgs/newer".to_string(),
+                ..flake_element(attr_path, true).source.unwrap()
+            }),
+            ..flake_element(attr_path, true)
+        }
+    }
+
+    fn store_path_element(store_path: &str) -> Element {
+        Element {
+            active: true,
+            store_paths: vec![store_path.to_string()],
+            priority: None,
+            source: None,
+        }
+    }
+
+    fn generation(name: &str, elements: Vec<Element>) -> Generation {
+        Generation {
+            name: name.to_string(),
+            metadata: GenerationMetadata {
+                created: 0,
+                last_active: 0,
+                log_message: Vec::new(),
+                path: PathBuf::new(),
+                version: 0,
+            },
+            elements,
+        }
+    }
+
+    #[test]
+    fn diff_generations() {
+        let old = generation("1", vec![
+            flake_element("hello", true),
+            flake_element("curl", true),
+            flake_element("jq", false),
+            flake_element("git", true),
+            store_path_element("/nix/store/hash-old"),
+        ]);
+        let new = generation("2", vec![
+            flake_element("hello", true),
+            flake_element("curl", false),
+            flake_element("jq", true),
+            upgraded_flake_element("git"),
+            store_path_element("/nix/store/hash-new"),
+        ]);
+
+        assert_eq!(old.diff(&new), GenerationDiff {
+            added: vec![
+                "/nix/store/hash-new".to_string(),
+                "github:flox/nixpkgs#jq".to_string(),
+            ],
+            removed: vec![
+                "/nix/store/hash-old".to_string(),
+                "github:flox/nixpkgs#curl".to_string(),
+            ],
+            changed: vec!["github:flox/nixpkgs#git".to_string()],
+        });
+        assert_eq!(new.diff(&new), GenerationDiff::default());
+    }
+}
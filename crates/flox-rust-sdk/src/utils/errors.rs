@@ -9,6 +9,10 @@ pub enum IoError {
     CreateTempDir { dir: PathBuf, err: io::Error },
     #[error("Couldn't open {file}: {err}")]
     Open { file: PathBuf, err: io::Error },
+    #[error("Couldn't read {file}: {err}")]
+    Read { file: PathBuf, err: io::Error },
+    #[error("Couldn't remove {file}: {err}")]
+    Remove { file: PathBuf, err: io::Error },
     #[error("Couldn't copy {file}: {err}")]
     Copy { file: PathBuf, err: io::Error },
     #[error("Couldn't write {file}: {err}")]
@@ -3,13 +3,18 @@ use std::path::PathBuf;
 use anyhow::Result;
 use bpaf::{construct, Bpaf, Parser, ShellComp};
 use flox_rust_sdk::flox::Flox;
+use flox_rust_sdk::models::root::environment::GetEnvironmentsError;
 use flox_rust_sdk::models::root::floxmeta::Floxmeta;
 use flox_rust_sdk::nix::command_line::NixCommandLine;
 use flox_rust_sdk::prelude::flox_package::FloxPackage;
 use flox_rust_sdk::providers::git::{GitCommandProvider, GitProvider};
+use log::warn;
 use serde_json::json;
+use time::format_description::well_known::Iso8601;
+use time::OffsetDateTime;
 
 use crate::config::features::Feature;
+use crate::utils::complete_environment;
 use crate::{flox_forward, subcommand_metric};
 
 #[derive(Bpaf, Clone)]
@@ -29,24 +34,8 @@ impl EnvironmentCommands {
                 json: _,
                 generation: _,
             } if !Feature::Env.is_forwarded()? => {
-                let name = environment
-                    .as_ref()
-                    .map(|path| path.as_os_str().to_string_lossy())
-                    .unwrap_or_else(|| "default".into());
-
-                // todo rename project!
-                // this is now just a root
-
-                // assume local for now. next, parse environment
-                // assume local exists
-                let floxmeta = flox
-                    .project(flox.cache_dir.join("meta").join("local"))
-                    .guard::<GitCommandProvider>()
-                    .await?
-                    .open()
-                    .expect("Expected repository exist")
-                    .guard_floxmeta()
-                    .await?;
+                let name = environment_name(environment);
+                let floxmeta = local_floxmeta(&flox).await?;
 
                 let environment = floxmeta.environment(&name).await?;
                 let metadata = environment.metadata().await?;
@@ -55,13 +44,99 @@ impl EnvironmentCommands {
                 println!("{}", serde_json::to_string_pretty(&generation).unwrap())
             },
 
-            EnvironmentCommands::Envs if !Feature::Env.is_forwarded()? => {
+            // diffing generations is not supported by the forwarded implementation
+            EnvironmentCommands::Generations {
+                environment_args: _,
+                json,
+                environment,
+                diff: Some(GenerationsDiff { from, to, .. }),
+            } => {
+                subcommand_metric!("generations");
+
+                let name = environment_name(environment);
+                let floxmeta = local_floxmeta(&flox).await?;
+                let environment = floxmeta.environment(&name).await?;
+
+                let from = environment.generation(&from.to_string()).await?;
+                let to = environment.generation(&to.to_string()).await?;
+                let diff = from.diff(&to);
+
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&diff)?);
+                } else {
+                    for package in &diff.removed {
+                        println!("- {package}");
+                    }
+                    for package in &diff.added {
+                        println!("+ {package}");
+                    }
+                    for package in &diff.changed {
+                        println!("~ {package}");
+                    }
+                }
+            },
+
+            EnvironmentCommands::Generations {
+                environment_args: _,
+                json,
+                environment,
+                diff: None,
+            } if !Feature::Env.is_forwarded()? => {
+                subcommand_metric!("generations");
+
+                let name = environment_name(environment);
+                let floxmeta = local_floxmeta(&flox).await?;
+                let environment = floxmeta.environment(&name).await?;
+                let metadata = environment.metadata().await?;
+                let generations = environment.generations().await?;
+
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&generations)?);
+                } else {
+                    for generation in generations {
+                        let current = if generation.name == metadata.current_gen {
+                            " (current)"
+                        } else {
+                            ""
+                        };
+                        let created = OffsetDateTime::from_unix_timestamp(
+                            generation.metadata.created as i64,
+                        )?
+                        .format(&Iso8601::DEFAULT)?;
+
+                        println!("Generation {}{current}:", generation.name);
+                        println!("  Created: {created}");
+                        for message in &generation.metadata.log_message {
+                            println!("  {message}");
+                        }
+                    }
+                }
+            },
+
+            // flox-bash can not list environments offline
+            EnvironmentCommands::Envs { offline }
+                if *offline || !Feature::Env.is_forwarded()? =>
+            {
                 let floxmetas = Floxmeta::<GitCommandProvider>::list_floxmetas(&flox).await?;
 
                 let mut values = Vec::new();
 
                 for meta in floxmetas {
-                    let envs = meta.environments().await?;
+                    let envs = if *offline {
+                        meta.local_environments().await?
+                    } else {
+                        match meta.environments().await {
+                            Ok(envs) => envs,
+                            Err(GetEnvironmentsError::FetchBranches(err)) => {
+                                warn!(
+                                    "Could not sync environments, \
+                                     listing the last synced state: {err}"
+                                );
+                                meta.local_environments().await?
+                            },
+                            Err(err) => Err(err)?,
+                        }
+                    };
                     let mut dir = meta.git.workdir();
                     let dir = dir.get_or_insert_with(|| meta.git.path());
 
@@ -94,6 +169,29 @@ impl EnvironmentCommands {
     }
 }
 
+fn environment_name(environment: &Option<EnvironmentRef>) -> String {
+    environment
+        .as_ref()
+        .map(|path| path.as_os_str().to_string_lossy().into_owned())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Open the floxmeta repository holding local environments
+///
+/// todo: resolve the floxmeta repository from the environment reference
+async fn local_floxmeta(flox: &Flox) -> Result<Floxmeta<GitCommandProvider>> {
+    let floxmeta = flox
+        .project(flox.cache_dir.join("meta").join("local"))
+        .guard::<GitCommandProvider>()
+        .await?
+        .open()
+        .expect("Expected repository exist")
+        .guard_floxmeta()
+        .await?;
+
+    Ok(floxmeta)
+}
+
 fn activate_run_args() -> impl Parser<Option<(String, Vec<String>)>> {
     let command = bpaf::positional("COMMAND").strict();
     let args = bpaf::any("ARGUMENTS").many();
@@ -154,7 +252,11 @@ pub enum EnvironmentCommands {
     /// Aliases:
     ///   environments, envs
     #[bpaf(command, long("environments"))]
-    Envs,
+    Envs {
+        /// list environments as of the last sync, without network access
+        #[bpaf(long)]
+        offline: bool,
+    },
 
     /// activate environment:
     ///
@@ -166,7 +268,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Vec<EnvironmentRef>,
 
         #[bpaf(external(activate_run_args))]
@@ -179,7 +281,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 
@@ -195,7 +297,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 
@@ -205,7 +307,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 
@@ -215,7 +317,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 
@@ -228,8 +330,11 @@ pub enum EnvironmentCommands {
         #[bpaf(long)]
         json: bool,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
+
+        #[bpaf(external(generations_diff), optional)]
+        diff: Option<GenerationsDiff>,
     },
 
     /// access to the git CLI for floxmeta repository
@@ -238,7 +343,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(any("Git Arguments"))]
@@ -254,7 +359,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 
@@ -264,7 +369,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(external(ImportFile::parse), fallback(ImportFile::Stdin))]
@@ -277,7 +382,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(positional("PACKAGES"), some("At least one package"))]
@@ -290,7 +395,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(external(list_output), optional)]
@@ -335,7 +440,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(positional("PACKAGES"), some("At least one package"))]
@@ -348,7 +453,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         /// Generation to roll back to.
@@ -364,7 +469,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(positional("GENERATION"))]
@@ -377,7 +482,7 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
 
         #[bpaf(positional("PACKAGES"))]
@@ -390,11 +495,25 @@ pub enum EnvironmentCommands {
         #[bpaf(external(environment_args), group_help("Environment Options"))]
         environment_args: EnvironmentArgs,
 
-        #[bpaf(long, short, argument("ENV"))]
+        #[bpaf(long, short, argument("ENV"), complete(complete_environment))]
         environment: Option<EnvironmentRef>,
     },
 }
 
+/// Arguments for `flox generations --diff`
+#[derive(Debug, Clone, Bpaf)]
+#[bpaf(adjacent)]
+pub struct GenerationsDiff {
+    /// show packages added, removed and changed between two generations
+    diff: (),
+    /// Generation to compare from
+    #[bpaf(positional("FROM"))]
+    from: u32,
+    /// Generation to compare to
+    #[bpaf(positional("TO"))]
+    to: u32,
+}
+
 #[derive(Bpaf, Clone)]
 pub enum ListOutput {
     /// Include store paths of packages in the environment
@@ -1,22 +1,24 @@
 use std::env;
 use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use bpaf::{Bpaf, Parser};
+use flox_rust_sdk::actions::gc::GarbageCollection;
 use flox_rust_sdk::flox::Flox;
 use flox_rust_sdk::nix::command_line::{Group, NixCliCommand, NixCommandLine, ToArgs};
 use flox_rust_sdk::nix::Run;
 use flox_rust_sdk::prelude::{Channel, Stability};
-use fslock::LockFile;
 
 use crate::config::features::Feature;
-use crate::config::Config;
+use crate::config::{validate, Config};
 use crate::utils::init::init_telemetry_consent;
+use crate::utils::lock::Lock;
 use crate::utils::metrics::{
     METRICS_EVENTS_FILE_NAME,
     METRICS_LOCK_FILE_NAME,
     METRICS_UUID_FILE_NAME,
 };
+use crate::utils::plugins::Plugin;
 use crate::{flox_forward, subcommand_metric};
 
 #[derive(Bpaf, Clone)]
@@ -54,30 +56,124 @@ impl GeneralCommands {
             },
 
             GeneralCommands::ResetMetrics => {
-                let mut metrics_lock =
-                    LockFile::open(&flox.cache_dir.join(METRICS_LOCK_FILE_NAME))?;
-                tokio::task::spawn_blocking(move || metrics_lock.lock()).await??;
-
-                if let Err(err) =
-                    tokio::fs::remove_file(flox.cache_dir.join(METRICS_EVENTS_FILE_NAME)).await
                 {
-                    match err.kind() {
-                        std::io::ErrorKind::NotFound => {},
-                        _ => Err(err)?,
+                    // released before prompting, `init_telemetry_consent` takes the lock itself
+                    let _metrics_lock =
+                        Lock::acquire(flox.cache_dir.join(METRICS_LOCK_FILE_NAME)).await?;
+
+                    if let Err(err) =
+                        tokio::fs::remove_file(flox.cache_dir.join(METRICS_EVENTS_FILE_NAME)).await
+                    {
+                        match err.kind() {
+                            std::io::ErrorKind::NotFound => {},
+                            _ => Err(err)?,
+                        }
                     }
-                }
 
-                if let Err(err) =
-                    tokio::fs::remove_file(flox.data_dir.join(METRICS_UUID_FILE_NAME)).await
-                {
-                    match err.kind() {
-                        std::io::ErrorKind::NotFound => {},
-                        _ => Err(err)?,
+                    if let Err(err) =
+                        tokio::fs::remove_file(flox.data_dir.join(METRICS_UUID_FILE_NAME)).await
+                    {
+                        match err.kind() {
+                            std::io::ErrorKind::NotFound => {},
+                            _ => Err(err)?,
+                        }
                     }
                 }
 
                 init_telemetry_consent(&flox.data_dir, &flox.cache_dir).await?;
             },
+
+            GeneralCommands::Config(ConfigArgs::Validate) => {
+                subcommand_metric!("config");
+
+                let project_file = Config::project_file();
+                let mut unknown_keys = Vec::new();
+                for file in config.files() {
+                    let project = project_file.as_ref() == Some(&file);
+                    unknown_keys.extend(validate::unknown_keys(&file, project)?);
+                }
+
+                if !unknown_keys.is_empty() {
+                    for unknown_key in unknown_keys {
+                        println!("{unknown_key}");
+                    }
+                    bail!("Configuration contains unknown or misplaced keys");
+                }
+
+                println!("Configuration is valid");
+            },
+
+            GeneralCommands::Gc { dry_run } => {
+                subcommand_metric!("gc");
+
+                let removed = GarbageCollection::new(&flox, *dry_run).process_dirs()?;
+
+                for path in &removed {
+                    if *dry_run {
+                        println!("Would remove {}", path.display());
+                    } else {
+                        println!("Removed {}", path.display());
+                    }
+                }
+
+                if removed.is_empty() {
+                    println!("Nothing to remove");
+                }
+            },
+
+            GeneralCommands::Plugins(PluginsCommands::List) => {
+                subcommand_metric!("plugins");
+
+                for plugin in Plugin::discover() {
+                    println!("{}\t{}", plugin.name, plugin.path.display());
+                }
+            },
+
+            GeneralCommands::Plugins(PluginsCommands::Doctor) => {
+                subcommand_metric!("plugins");
+
+                let candidates = Plugin::candidates();
+                let mut problems = 0;
+
+                for (index, plugin) in candidates.iter().enumerate() {
+                    if !plugin.is_executable() {
+                        problems += 1;
+                        println!(
+                            "{}: {} is not executable and will be ignored",
+                            plugin.name,
+                            plugin.path.display()
+                        );
+                        continue;
+                    }
+
+                    if plugin.is_shadowed_by_builtin() {
+                        problems += 1;
+                        println!(
+                            "{}: {} is shadowed by the built-in command `flox {}`",
+                            plugin.name,
+                            plugin.path.display(),
+                            plugin.name
+                        );
+                    }
+
+                    if let Some(active) = candidates[..index]
+                        .iter()
+                        .find(|other| other.name == plugin.name && other.is_executable())
+                    {
+                        problems += 1;
+                        println!(
+                            "{}: {} is shadowed by {} found earlier on $PATH",
+                            plugin.name,
+                            plugin.path.display(),
+                            active.path.display()
+                        );
+                    }
+                }
+
+                if problems == 0 {
+                    println!("No problems found");
+                }
+            },
             _ if Feature::All.is_forwarded()? => flox_forward(&flox).await?,
             _ => todo!(),
         }
@@ -100,10 +196,32 @@ pub enum GeneralCommands {
     #[bpaf(command("reset-metrics"))]
     ResetMetrics,
 
+    /// remove temporary files left behind by earlier flox invocations
+    #[bpaf(command)]
+    Gc {
+        /// only list what would be removed
+        #[bpaf(long("dry-run"))]
+        dry_run: bool,
+    },
+
+    /// list and diagnose `flox-<name>` plugins found on $PATH
+    #[bpaf(command)]
+    Plugins(#[bpaf(external(plugins_commands), fallback(PluginsCommands::List))] PluginsCommands),
+
     /// access to the nix CLI
     Nix(#[bpaf(external(parse_nix_passthru))] WrappedNix),
 }
 
+#[derive(Bpaf, Clone)]
+pub enum PluginsCommands {
+    /// list plugins that can be invoked as `flox <name>`
+    #[bpaf(command)]
+    List,
+    /// report plugins that are not executable or shadowed
+    #[bpaf(command)]
+    Doctor,
+}
+
 #[derive(Bpaf, Clone)]
 pub enum ConfigArgs {
     /// list the current values of all configurable paramers
@@ -115,6 +233,9 @@ pub enum ConfigArgs {
     /// reset all configurable parameters to their default values without further confirmation.
     #[bpaf(short, long)]
     Confirm,
+    /// check config files for unknown or misspelled keys
+    #[bpaf(long)]
+    Validate,
 
     Set(#[bpaf(external(config_set))] ConfigSet),
     SetNumber(#[bpaf(external(config_set_number))] ConfigSetNumber),
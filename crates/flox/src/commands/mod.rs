@@ -8,6 +8,7 @@ use std::{env, fs};
 
 use anyhow::Result;
 use bpaf::{Bpaf, Parser};
+use flox_rust_sdk::actions::gc::{record_process_dir_owner, PROCESS_DIR_NAME};
 use flox_rust_sdk::flox::{Flox, FLOX_VERSION};
 use flox_rust_sdk::prelude::Channel;
 use log::debug;
@@ -21,8 +22,10 @@ use crate::utils::init::{
     init_access_tokens,
     init_channels,
     init_git_conf,
+    init_substituters,
     init_telemetry_consent,
     init_uuid,
+    substituters_trust_file,
 };
 
 fn vec_len<T>(x: Vec<T>) -> usize {
@@ -78,12 +81,13 @@ impl FloxArgs {
         tokio::fs::create_dir_all(&config.flox.data_dir).await?;
 
         // prepare a temp dir for the run:
-        let process_dir = config.flox.cache_dir.join("process");
+        let process_dir = config.flox.cache_dir.join(PROCESS_DIR_NAME);
         tokio::fs::create_dir_all(&process_dir).await?;
 
         // `temp_dir` will automatically be removed from disk when the function returns
         let temp_dir = TempDir::new_in(process_dir)?;
         let temp_dir_path = temp_dir.path().to_owned();
+        record_process_dir_owner(&temp_dir_path)?;
 
         init_git_conf(temp_dir.path(), &config.flox.config_dir).await?;
 
@@ -101,6 +105,9 @@ impl FloxArgs {
 
         let access_tokens = init_access_tokens(&config.nix.access_tokens)?;
 
+        let substituters =
+            init_substituters(&config.nix.substituters, &substituters_trust_file()?).await?;
+
         let netrc_file = dirs::home_dir()
             .expect("User must have a home directory")
             .join(".netrc");
@@ -112,6 +119,7 @@ impl FloxArgs {
             channels,
             access_tokens,
             netrc_file,
+            substituters,
             temp_dir: temp_dir_path.clone(),
             system: env!("NIX_TARGET_SYSTEM").to_string(),
             uuid: init_uuid(&config.flox.data_dir).await?,
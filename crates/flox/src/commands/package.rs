@@ -57,7 +57,7 @@ pub(crate) mod interface {
         ShellInstallable,
         TemplateInstallable,
     };
-    use crate::utils::{InstallableArgument, InstallableDef, Parsed};
+    use crate::utils::{complete_environment, InstallableArgument, InstallableDef, Parsed};
 
     #[derive(Clone, Debug)]
     pub enum PosOrEnv<T: InstallableDef> {
@@ -236,7 +236,12 @@ pub(crate) mod interface {
     #[derive(Bpaf, Clone, Debug)]
     pub struct Containerize {
         /// Environment to containerize
-        #[bpaf(long("environment"), short('e'), argument("ENV"))]
+        #[bpaf(
+            long("environment"),
+            short('e'),
+            argument("ENV"),
+            complete(complete_environment)
+        )]
         pub(crate) environment_name: Option<String>,
 
         #[bpaf(short('A'), hide)]
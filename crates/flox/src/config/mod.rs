@@ -1,12 +1,12 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
 use anyhow::{Context, Result};
-use config::{Config as HierarchicalConfig, Environment};
+use config::{Config as HierarchicalConfig, Environment, FileFormat, Source, ValueKind};
 use flox_rust_sdk::prelude::Stability;
 use itertools::{Either, Itertools};
-use log::debug;
+use log::{debug, warn};
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
@@ -15,6 +15,15 @@ use xdg::BaseDirectories;
 /// Name of flox managed directories (config, data, cache)
 const FLOX_DIR_NAME: &'_ str = "flox";
 
+/// Project specific config file, looked up in the current directory and its parents
+const PROJECT_CONFIG_FILE: &'_ str = ".flox/config.toml";
+
+/// Top level keys a project config may set
+///
+/// Project config files come with the project, e.g. a cloned repository,
+/// so they must not relocate the directories holding flox' own state.
+pub(crate) const PROJECT_CONFIG_KEYS: &[&str] = &["stability", "features", "nix.substituters"];
+
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct Config {
     /// flox configuration options
@@ -52,13 +61,19 @@ pub struct FloxConfig {
 /// Describes the nix config under flox
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct NixConfig {
+    #[serde(default)]
     pub access_tokens: HashMap<String, String>,
+
+    /// Additional binary caches, mapping substituter URLs to their public key
+    #[serde(default)]
+    pub substituters: HashMap<String, String>,
 }
 
 /// Describes the github config under flox
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct GithubConfig {}
 pub mod features;
+pub mod validate;
 
 impl Config {
     /// Creates a raw [Config] object and caches it for the lifetime of the program
@@ -68,7 +83,7 @@ impl Config {
             let flox_dirs = BaseDirectories::with_prefix(FLOX_DIR_NAME)?;
 
             let cache_dir = flox_dirs.get_cache_home();
-            let data_dir = flox_dirs.get_data_home();
+            let data_dir = xdg_data_dir()?;
             let config_dir = match env::var("FLOX_CONFIG_HOME") {
                 Ok(v) => {
                     debug!("`$FLOX_CONFIG_HOME` set: {v}");
@@ -90,29 +105,19 @@ impl Config {
                 },
             };
 
-            let builder = HierarchicalConfig::builder()
-                .set_default("cache_dir", cache_dir.to_str().unwrap())?
-                .set_default("data_dir", data_dir.to_str().unwrap())?
-                // config dir is added to the config for completenes, the config file cannot chenge the config dir
-                .set_default("config_dir", config_dir.to_str().unwrap())?
-                .add_source(
-                    config::File::with_name("flox")
-                        .required(false),
-                );
-
-            let mut flox_envs = env::vars()
+            let project_config = Self::project_file();
+
+            let flox_envs = env::vars()
                 .filter_map(|(k, v)| k.strip_prefix("FLOX_").map(|k| (k.to_owned(), v)))
                 .collect::<Vec<_>>();
 
-            let builder = builder
-                .add_source(mk_environment(&mut flox_envs, "NIX"))
-                .add_source(mk_environment(&mut flox_envs, "GITHUB"))
-                .add_source(mk_environment(&mut flox_envs, "FEATURES"))
-                .add_source(Environment::default().source(Some(HashMap::from_iter(flox_envs))));
-
-            let final_config = builder.build()?;
-
-            Ok(final_config)
+            build_config(
+                &cache_dir,
+                &data_dir,
+                &config_dir,
+                project_config.as_deref(),
+                flox_envs,
+            )
         })
     }
 
@@ -125,6 +130,119 @@ impl Config {
             .context("Could not parse config")?;
         Ok(cli_confg)
     }
+
+    /// The TOML config files read by [Config::parse], lowest precedence first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let user_config = self.flox.config_dir.join(format!("{FLOX_DIR_NAME}.toml"));
+
+        [Some(user_config), Self::project_file()]
+            .into_iter()
+            .flatten()
+            .filter(|file| file.is_file())
+            .collect()
+    }
+
+    /// The project config file applying to the current directory, if any
+    pub fn project_file() -> Option<PathBuf> {
+        env::current_dir()
+            .ok()
+            .and_then(|cwd| find_project_config(&cwd))
+    }
+}
+
+/// The default data directory, which config files can not change
+///
+/// For state that must not be controlled by config, like the trust decisions
+/// for substituters added through config.
+pub fn xdg_data_dir() -> Result<PathBuf> {
+    Ok(BaseDirectories::with_prefix(FLOX_DIR_NAME)?.get_data_home())
+}
+
+/// Layer defaults, user config, project config and `FLOX_*` variables (`flox_envs`)
+pub(crate) fn build_config(
+    cache_dir: &Path,
+    data_dir: &Path,
+    config_dir: &Path,
+    project_config: Option<&Path>,
+    mut flox_envs: Vec<(String, String)>,
+) -> Result<HierarchicalConfig> {
+    let mut builder = HierarchicalConfig::builder()
+        .set_default("cache_dir", cache_dir.to_str().unwrap())?
+        .set_default("data_dir", data_dir.to_str().unwrap())?
+        // config dir is added to the config for completeness,
+        // setting it in a config file does not change where the user config is read from
+        .set_default("config_dir", config_dir.to_str().unwrap())?
+        .add_source(
+            config::File::with_name(config_dir.join(FLOX_DIR_NAME).to_str().unwrap())
+                .required(false),
+        );
+
+    // project config overrides the user config
+    if let Some(project_config) = project_config {
+        debug!("Using project config {project_config:?}");
+        builder = builder.add_source(ProjectConfig(project_config.to_path_buf()));
+    }
+
+    let builder = builder
+        .add_source(mk_environment(&mut flox_envs, "NIX"))
+        .add_source(mk_environment(&mut flox_envs, "GITHUB"))
+        .add_source(mk_environment(&mut flox_envs, "FEATURES"))
+        .add_source(Environment::default().source(Some(HashMap::from_iter(flox_envs))));
+
+    Ok(builder.build()?)
+}
+
+/// A project config file, restricted to [PROJECT_CONFIG_KEYS]
+#[derive(Debug, Clone)]
+struct ProjectConfig(PathBuf);
+
+impl Source for ProjectConfig {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<config::Map<String, config::Value>, config::ConfigError> {
+        let mut values = config::File::new(self.0.to_str().unwrap(), FileFormat::Toml).collect()?;
+        self.restrict(&mut values, "");
+        Ok(values)
+    }
+}
+
+impl ProjectConfig {
+    /// Drop all keys below `prefix` that are not (parents of) [PROJECT_CONFIG_KEYS]
+    fn restrict(&self, table: &mut config::Map<String, config::Value>, prefix: &str) {
+        table.retain(|key, value| {
+            let path = format!("{prefix}{key}");
+            if PROJECT_CONFIG_KEYS.contains(&path.as_str()) {
+                return true;
+            }
+
+            let nested_prefix = format!("{path}.");
+            if PROJECT_CONFIG_KEYS
+                .iter()
+                .any(|allowed| allowed.starts_with(&nested_prefix))
+            {
+                if let ValueKind::Table(nested) = &mut value.kind {
+                    self.restrict(nested, &nested_prefix);
+                    return true;
+                }
+            }
+
+            warn!(
+                "Ignoring '{path}' in project config {:?}, it can only be set in the user config",
+                self.0
+            );
+            false
+        });
+    }
+}
+
+/// Find the closest [PROJECT_CONFIG_FILE] in `start` or any of its parents
+fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
 }
 
 fn mk_environment(envs: &mut Vec<(String, String)>, prefix: &str) -> Environment {
@@ -141,3 +259,86 @@ fn mk_environment(envs: &mut Vec<(String, String)>, prefix: &str) -> Environment
     *envs = flox_envs;
     environment
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_project_config(dir: &Path, content: &str) -> PathBuf {
+        let file = dir.join(PROJECT_CONFIG_FILE);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, content).unwrap();
+        file
+    }
+
+    #[test]
+    fn finds_closest_project_config() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let outer = write_project_config(tempdir.path(), "");
+        let nested = tempdir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(find_project_config(&nested), Some(outer));
+
+        let inner = write_project_config(&tempdir.path().join("a"), "");
+        assert_eq!(find_project_config(&nested), Some(inner));
+    }
+
+    #[test]
+    fn config_precedence() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let config_dir = tempdir.path().join("config");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(
+            config_dir.join("flox.toml"),
+            "stability = \"user\"\n[nix.substituters]\ncache = \"user\"\n",
+        )
+        .unwrap();
+        let project_config =
+            write_project_config(tempdir.path(), "[nix.substituters]\ncache = \"project\"\n");
+
+        let build = |envs: Vec<(String, String)>| {
+            build_config(
+                Path::new("/cache"),
+                Path::new("/data"),
+                &config_dir,
+                Some(&project_config),
+                envs,
+            )
+            .unwrap()
+        };
+
+        let config = build(Vec::new());
+        assert_eq!(config.get_string("stability").unwrap(), "user");
+        assert_eq!(
+            config.get_string("nix.substituters.cache").unwrap(),
+            "project"
+        );
+
+        let config = build(vec![("STABILITY".to_string(), "env".to_string())]);
+        assert_eq!(config.get_string("stability").unwrap(), "env");
+    }
+
+    #[test]
+    fn project_config_can_not_move_state_dirs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let project_config = write_project_config(
+            tempdir.path(),
+            "data_dir = \"./.flox/data\"\ncache_dir = \"./.flox/cache\"\nconfig_dir = \"./.flox\"\ndisable_metrics = \"true\"\n[nix.access_tokens]\n\"github.com\" = \"project\"\n",
+        );
+
+        let config = build_config(
+            Path::new("/cache"),
+            Path::new("/data"),
+            &tempdir.path().join("config"),
+            Some(&project_config),
+            Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(config.get_string("data_dir").unwrap(), "/data");
+        assert_eq!(config.get_string("cache_dir").unwrap(), "/cache");
+        assert!(config.get_string("disable_metrics").is_err());
+        assert!(config.get_table("nix.access_tokens").is_err());
+    }
+}
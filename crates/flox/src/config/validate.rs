@@ -0,0 +1,292 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use config::{FileFormat, Source, Value, ValueKind};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+
+use super::features::Feature;
+use super::PROJECT_CONFIG_KEYS;
+
+/// Keys accepted at the top level of a config file
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "disable_metrics",
+    "cache_dir",
+    "data_dir",
+    "config_dir",
+    "stability",
+    "nix",
+    "github",
+    "features",
+];
+
+/// Keys accepted in the `nix` table
+const NIX_KEYS: &[&str] = &["access_tokens", "substituters"];
+
+/// Keys accepted in the `github` table
+const GITHUB_KEYS: &[&str] = &[];
+
+/// A key in a config file that flox does not know about,
+/// or that can only be set in the user config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    pub file: PathBuf,
+    pub key: String,
+    pub suggestion: Option<String>,
+    pub user_only: bool,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.user_only {
+            return write!(
+                f,
+                "{}: '{}' can only be set in the user config",
+                self.file.display(),
+                self.key
+            );
+        }
+        write!(f, "{}: unknown key '{}'", self.file.display(), self.key)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean '{suggestion}'?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a TOML config file for keys flox does not know about
+///
+/// Project config files are also checked for keys outside of [PROJECT_CONFIG_KEYS].
+/// Values of known keys are checked by [super::Config::parse].
+pub fn unknown_keys(file: &Path, project: bool) -> Result<Vec<UnknownKey>> {
+    let table = config::File::new(file.to_str().unwrap(), FileFormat::Toml)
+        .collect()
+        .with_context(|| format!("Could not read config file {file:?}"))?;
+
+    let unknown_key = |key: String, known: &[&str]| {
+        let name = key.rsplit('.').next().unwrap_or(&key);
+        if known.contains(&name) {
+            return None;
+        }
+        let suggestion = suggest(name, known).map(|s| match key.rsplit_once('.') {
+            Some((parent, _)) => format!("{parent}.{s}"),
+            None => s.to_string(),
+        });
+        Some(UnknownKey {
+            file: file.to_path_buf(),
+            key,
+            suggestion,
+            user_only: false,
+        })
+    };
+
+    let user_only = if project {
+        user_only_keys(&table, "")
+    } else {
+        Vec::new()
+    };
+
+    let mut unknown = Vec::new();
+    for (key, value) in table {
+        match key.as_str() {
+            "nix" => unknown.extend(
+                table_keys(value)
+                    .into_iter()
+                    .filter_map(|nix_key| unknown_key(format!("nix.{nix_key}"), NIX_KEYS)),
+            ),
+            "github" => {
+                unknown.extend(table_keys(value).into_iter().filter_map(|github_key| {
+                    unknown_key(format!("github.{github_key}"), GITHUB_KEYS)
+                }))
+            },
+            "features" => unknown.extend(
+                table_keys(value)
+                    .into_iter()
+                    .filter(|feature| !is_feature(feature))
+                    .map(|feature| UnknownKey {
+                        file: file.to_path_buf(),
+                        key: format!("features.{feature}"),
+                        suggestion: None,
+                        user_only: false,
+                    }),
+            ),
+            _ => {},
+        }
+        unknown.extend(unknown_key(key, TOP_LEVEL_KEYS));
+    }
+
+    // unknown keys are not reported again as user only
+    unknown.extend(user_only.into_iter().map(|key| UnknownKey {
+        file: file.to_path_buf(),
+        key,
+        suggestion: None,
+        user_only: true,
+    }));
+    unknown.sort_by(|a, b| a.key.cmp(&b.key));
+    unknown.dedup_by(|later, earlier| later.key == earlier.key);
+    Ok(unknown)
+}
+
+/// Keys below `prefix` that are not (parents of) [PROJECT_CONFIG_KEYS]
+fn user_only_keys(table: &config::Map<String, Value>, prefix: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (key, value) in table {
+        let path = format!("{prefix}{key}");
+        if PROJECT_CONFIG_KEYS.contains(&path.as_str()) {
+            continue;
+        }
+
+        let nested_prefix = format!("{path}.");
+        match &value.kind {
+            ValueKind::Table(nested)
+                if PROJECT_CONFIG_KEYS
+                    .iter()
+                    .any(|allowed| allowed.starts_with(&nested_prefix)) =>
+            {
+                keys.extend(user_only_keys(nested, &nested_prefix))
+            },
+            _ => keys.push(path),
+        }
+    }
+    keys
+}
+
+fn table_keys(value: Value) -> Vec<String> {
+    match value.kind {
+        ValueKind::Table(table) => table.into_keys().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_feature(key: &str) -> bool {
+    Feature::deserialize(key.into_deserializer())
+        .map_err(|_: serde::de::value::Error| ())
+        .is_ok()
+}
+
+/// The closest known key, if any is similar enough to be a likely typo
+fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (levenshtein(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use flox_rust_sdk::prelude::Stability;
+
+    use super::*;
+    use crate::config::features::Impl;
+    use crate::config::Config;
+
+    /// Every key listed as known must be read by [Config]
+    #[test]
+    fn known_keys_are_config_fields() {
+        let nix_sample = |key: &str| match key {
+            "access_tokens" => "{ host = \"token\" }",
+            "substituters" => "{ host = \"key\" }",
+            _ => panic!("no sample value for 'nix.{key}'"),
+        };
+        let sample = |key: &str| match key {
+            "disable_metrics" => "\"true\"".to_string(),
+            "cache_dir" => "\"/cache\"".to_string(),
+            "data_dir" => "\"/data\"".to_string(),
+            "config_dir" => "\"/config\"".to_string(),
+            "stability" => "\"unstable\"".to_string(),
+            "nix" => format!(
+                "{{ {} }}",
+                NIX_KEYS
+                    .iter()
+                    .map(|key| format!("{key} = {}", nix_sample(key)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            "github" => "{}".to_string(),
+            "features" => "{ env = \"rust\" }".to_string(),
+            _ => panic!("no sample value for '{key}'"),
+        };
+        let toml = TOP_LEVEL_KEYS
+            .iter()
+            .map(|key| format!("{key} = {}\n", sample(key)))
+            .collect::<String>();
+
+        let config: Config = config::Config::builder()
+            .add_source(config::File::from_str(&toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert!(config.flox.disable_metrics);
+        assert_eq!(config.flox.cache_dir, PathBuf::from("/cache"));
+        assert_eq!(config.flox.data_dir, PathBuf::from("/data"));
+        assert_eq!(config.flox.config_dir, PathBuf::from("/config"));
+        assert_eq!(config.flox.stability, Stability::Unstable);
+        assert_eq!(config.nix.access_tokens["host"], "token");
+        assert_eq!(config.nix.substituters["host"], "key");
+        assert_eq!(config.features[&Feature::Env], Impl::Rust);
+    }
+
+    #[test]
+    fn checks_project_files_for_user_only_keys() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let file = tempdir.path().join("config.toml");
+        std::fs::write(
+            &file,
+            "stability = \"unstable\"\ncache_dir = \"/cache\"\nstabilty = \"stable\"\n[nix.access_tokens]\nhost = \"token\"\n[nix.substituters]\nhost = \"key\"\n",
+        )
+        .unwrap();
+
+        let keys = |project| {
+            unknown_keys(&file, project)
+                .unwrap()
+                .into_iter()
+                .map(|key| (key.key, key.user_only))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(false), vec![("stabilty".to_string(), false)]);
+        assert_eq!(keys(true), vec![
+            ("cache_dir".to_string(), true),
+            ("nix.access_tokens".to_string(), true),
+            ("stabilty".to_string(), false),
+        ]);
+    }
+
+    #[test]
+    fn checks_feature_keys() {
+        assert!(is_feature("env"));
+        assert!(!is_feature("envs"));
+    }
+
+    #[test]
+    fn suggests_close_keys() {
+        assert_eq!(
+            suggest("disable_metric", TOP_LEVEL_KEYS),
+            Some("disable_metrics")
+        );
+        assert_eq!(suggest("stabilty", TOP_LEVEL_KEYS), Some("stability"));
+        assert_eq!(suggest("something_else", TOP_LEVEL_KEYS), None);
+    }
+}
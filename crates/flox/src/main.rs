@@ -9,14 +9,14 @@ use anyhow::{anyhow, Context, Result};
 use bpaf::Parser;
 use commands::{BashPassthru, FloxArgs, Prefix};
 use flox_rust_sdk::environment::default_nix_subprocess_env;
-use fslock::LockFile;
 use itertools::Itertools;
 use log::{debug, error, warn};
 use serde_json::json;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::process::Command;
 use utils::init::init_logger;
-use utils::metrics::{METRICS_LOCK_FILE_NAME, METRICS_UUID_FILE_NAME};
+use utils::metrics::{metrics_lock, METRICS_UUID_FILE_NAME};
+use utils::plugins::Plugin;
 
 mod build;
 mod commands;
@@ -67,7 +67,27 @@ async fn main() -> ExitCode {
     };
     init_logger(Some(verbosity), Some(debug));
 
-    let args = commands::flox_args().try_run().map_err(|err| match err {
+    let args = commands::flox_args().try_run();
+
+    // Unknown subcommands may be provided by a `flox-<name>` plugin on `$PATH`
+    if let Err(bpaf::ParseFailure::Stderr(_)) = args {
+        let plugin_args = env::args_os()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect_vec();
+
+        if let Some((plugin, plugin_args)) = Plugin::from_args(&plugin_args) {
+            return run_plugin(&plugin, &plugin_args)
+                .await
+                .unwrap_or_else(|run_error| {
+                    error!("Error: {:?}", run_error);
+                    1u8
+                })
+                .into();
+        }
+    }
+
+    let args = args.map_err(|err| match err {
         bpaf::ParseFailure::Stdout(_) => err,
         bpaf::ParseFailure::Stderr(message) => {
             let mut help_args = env::args_os()
@@ -91,6 +111,11 @@ async fn main() -> ExitCode {
         match parse_err {
             bpaf::ParseFailure::Stdout(m) => {
                 print!("{m}");
+                if is_toplevel_help() {
+                    if let Some(plugins) = Plugin::help_section() {
+                        print!("{plugins}");
+                    }
+                }
                 return ExitCode::from(0);
             },
             bpaf::ParseFailure::Stderr(m) => {
@@ -136,8 +161,10 @@ pub async fn flox_forward(flox: &Flox) -> Result<()> {
 
 #[allow(clippy::bool_to_int_with_if)]
 async fn sync_bash_metrics_consent(data_dir: &Path, cache_dir: &Path) -> Result<()> {
-    let mut metrics_lock = LockFile::open(&cache_dir.join(METRICS_LOCK_FILE_NAME))?;
-    tokio::task::spawn_blocking(move || metrics_lock.lock()).await??;
+    let _metrics_lock = match metrics_lock(cache_dir).await {
+        Some(lock) => lock,
+        None => return Ok(()),
+    };
 
     let uuid_path = data_dir.join(METRICS_UUID_FILE_NAME);
 
@@ -214,9 +241,18 @@ pub async fn run_in_flox(
         sync_bash_metrics_consent(&flox.data_dir, &flox.cache_dir).await?;
     }
 
-    let status = Command::new(FLOX_SH)
-        .args(args)
-        .envs(&default_nix_subprocess_env())
+    let mut command = Command::new(FLOX_SH);
+    command.args(args).envs(&default_nix_subprocess_env());
+
+    if let Some(substituters) = flox.and_then(Flox::substituters_nix_config) {
+        let nix_config = match env::var("NIX_CONFIG") {
+            Ok(nix_config) => format!("{nix_config}\n{substituters}"),
+            Err(_) => substituters,
+        };
+        command.env("NIX_CONFIG", nix_config);
+    }
+
+    let status = command
         .spawn()
         .expect("failed to spawn flox")
         .wait()
@@ -232,6 +268,19 @@ pub async fn run_in_flox(
     Ok(code)
 }
 
+/// Whether flox was invoked as `flox [<general-options>] --help`
+fn is_toplevel_help() -> bool {
+    let args = env::args().skip(1).collect_vec();
+    args.iter().all(|arg| arg.starts_with('-'))
+        && args.iter().any(|arg| arg == "--help" || arg == "-h")
+}
+
+async fn run_plugin(plugin: &Plugin, args: &[String]) -> Result<u8> {
+    set_user()?;
+    set_parent_process_id();
+    plugin.run(&config::Config::parse()?, args).await
+}
+
 /// Resets the `$USER`/`HOME` variables to match `euid`
 ///
 /// Files written by `sudo flox ...` / `su`,
@@ -2,11 +2,14 @@ use std::collections::{HashMap, HashSet};
 
 use anyhow::{bail, Result};
 use async_trait::async_trait;
+use flox_rust_sdk::actions::gc::{record_process_dir_owner, PROCESS_DIR_NAME};
 use flox_rust_sdk::flox::{Flox, FloxInstallable};
+use flox_rust_sdk::models::root::floxmeta::Floxmeta;
+use flox_rust_sdk::providers::git::{GitCommandProvider, GitProvider};
 use log::debug;
 use tempfile::TempDir;
 
-use super::init::init_access_tokens;
+use super::init::{init_access_tokens, read_substituters, substituters_trust_file};
 use super::{init_channels, nix_str_safe};
 use crate::config::Config;
 
@@ -27,6 +30,11 @@ where
         default_flakerefs: &[&str],
         default_attr_prefixes: &[(&str, bool)],
     ) -> Result<Vec<String>>;
+
+    /// Complete the name of an environment for the current system
+    ///
+    /// Only reads the local floxmeta clones, so this works offline.
+    async fn complete_environment(&self, environment_str: &str) -> Result<Vec<String>>;
 }
 
 #[async_trait]
@@ -40,7 +48,7 @@ impl FloxCompletionExt for Flox {
             .map_err(|e| debug!("Failed to initialize channels: {e}"))
             .unwrap();
 
-        let process_dir = config.flox.cache_dir.join("process");
+        let process_dir = config.flox.cache_dir.join(PROCESS_DIR_NAME);
         match std::fs::create_dir_all(&process_dir) {
             Ok(_) => {},
             Err(e) => {
@@ -55,10 +63,19 @@ impl FloxCompletionExt for Flox {
             },
         };
 
+        if let Err(e) = record_process_dir_owner(temp_dir.path()) {
+            debug!("Failed to record owner of temp_dir: {e}");
+        }
+
         let access_tokens = init_access_tokens(&config.nix.access_tokens)
             .map_err(|e| debug!("Failed to initialize access tokens: {e}"))
             .unwrap_or_default();
 
+        let substituters = substituters_trust_file()
+            .and_then(|trust_file| read_substituters(&config.nix.substituters, &trust_file))
+            .map_err(|e| debug!("Failed to read trusted substituters: {e}"))
+            .unwrap_or_default();
+
         let netrc_file = dirs::home_dir()
             .expect("User must have a home directory")
             .join(".netrc");
@@ -72,6 +89,7 @@ impl FloxCompletionExt for Flox {
             system: env!("NIX_TARGET_SYSTEM").to_string(),
             netrc_file,
             access_tokens,
+            substituters,
             uuid: uuid::Uuid::nil(),
         })
     }
@@ -192,4 +210,37 @@ impl FloxCompletionExt for Flox {
 
         Ok(completions)
     }
+
+    async fn complete_environment(&self, environment_str: &str) -> Result<Vec<String>> {
+        let floxmetas = Floxmeta::<GitCommandProvider>::list_floxmetas(self).await?;
+
+        let mut completions = Vec::new();
+        for floxmeta in floxmetas {
+            let mut dir = floxmeta.git.workdir();
+            let owner = dir
+                .get_or_insert_with(|| floxmeta.git.path())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            for environment in floxmeta.local_environments().await? {
+                if environment.system() != self.system {
+                    continue;
+                }
+
+                // environments of the local floxmeta are referred to by name only
+                if owner == "local" {
+                    completions.push(environment.name().to_string());
+                } else {
+                    completions.push(format!("{owner}/{}", environment.name()));
+                }
+            }
+        }
+
+        completions.retain(|c| c.starts_with(environment_str));
+        completions.sort();
+        completions.dedup();
+
+        Ok(completions)
+    }
 }
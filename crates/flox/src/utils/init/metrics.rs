@@ -1,14 +1,13 @@
 use std::path::Path;
 
 use anyhow::{Context, Result};
-use fslock::LockFile;
 use indoc::formatdoc;
 use log::{debug, info, trace};
 use time::OffsetDateTime;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::utils::dialog::{Confirm, Dialog};
-use crate::utils::metrics::{MetricEntry, METRICS_LOCK_FILE_NAME, METRICS_UUID_FILE_NAME};
+use crate::utils::metrics::{metrics_lock, MetricEntry, METRICS_UUID_FILE_NAME};
 
 async fn write_metrics_uuid(uuid_path: &Path, consent: bool) -> Result<()> {
     let mut file = tokio::fs::File::create(&uuid_path).await?;
@@ -19,6 +18,25 @@ async fn write_metrics_uuid(uuid_path: &Path, consent: bool) -> Result<()> {
     Ok(())
 }
 
+/// Record the consent decision, unless another flox process did so in the meantime
+///
+/// The metrics lock is only taken here, so that other flox processes are not
+/// blocked while the user is prompted for consent.
+async fn write_consent(data_dir: &Path, cache_dir: &Path, consent: bool) -> Result<()> {
+    let _metrics_lock = match metrics_lock(cache_dir).await {
+        Some(lock) => lock,
+        None => return Ok(()),
+    };
+
+    let uuid_path = data_dir.join(METRICS_UUID_FILE_NAME);
+    if uuid_path.exists() {
+        debug!("Metrics consent was determined by another flox process");
+        return Ok(());
+    }
+
+    write_metrics_uuid(&uuid_path, consent).await
+}
+
 pub async fn init_telemetry_consent(data_dir: &Path, cache_dir: &Path) -> Result<()> {
     tokio::fs::create_dir_all(data_dir).await?;
 
@@ -27,9 +45,6 @@ pub async fn init_telemetry_consent(data_dir: &Path, cache_dir: &Path) -> Result
         return Ok(());
     }
 
-    let mut metrics_lock = LockFile::open(&cache_dir.join(METRICS_LOCK_FILE_NAME))?;
-    tokio::task::spawn_blocking(move || metrics_lock.lock()).await??;
-
     let uuid_path = data_dir.join(METRICS_UUID_FILE_NAME);
 
     match tokio::fs::File::open(&uuid_path).await {
@@ -56,7 +71,7 @@ pub async fn init_telemetry_consent(data_dir: &Path, cache_dir: &Path) -> Result
 
         if let Some(x) = json["floxMetricsConsent"].as_u64() {
             debug!("Using metrics consent value from bash flox");
-            write_metrics_uuid(&uuid_path, x == 1).await?;
+            write_consent(data_dir, cache_dir, x == 1).await?;
             return Ok(());
         }
     }
@@ -101,7 +116,7 @@ pub async fn init_telemetry_consent(data_dir: &Path, cache_dir: &Path) -> Result
     let consent = dialog.prompt().await?;
 
     if consent {
-        write_metrics_uuid(&uuid_path, true).await?;
+        write_consent(data_dir, cache_dir, true).await?;
         info!("\nThank you for helping to improve flox!\n");
     } else {
         let dialog = Dialog {
@@ -116,7 +131,7 @@ pub async fn init_telemetry_consent(data_dir: &Path, cache_dir: &Path) -> Result
 
         // TODO log if Refuse
 
-        write_metrics_uuid(&uuid_path, false).await?;
+        write_consent(data_dir, cache_dir, false).await?;
         info!("\nUnderstood. If you change your mind you can change your election\nat any time with the following command: flox reset-metrics\n");
     }
 
@@ -18,8 +18,10 @@ const ENV_GIT_CONFIG_SYSTEM: &str = "GIT_CONFIG_SYSTEM";
 const ENV_FLOX_ORIGINAL_GIT_CONFIG_SYSTEM: &str = "FLOX_ORIGINAL_GIT_CONFIG_SYSTEM";
 
 mod channels;
+mod substituters;
 
 pub use channels::init_channels;
+pub use substituters::{init_substituters, read_substituters, substituters_trust_file};
 
 pub fn init_access_tokens(
     config_tokens: &HashMap<String, String>,
@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use indoc::formatdoc;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::xdg_data_dir;
+use crate::utils::dialog::{Confirm, Dialog};
+
+/// File in the default data dir recording which configured substituters the user trusts
+const SUBSTITUTERS_TRUST_FILE_NAME: &str = "substituters.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TrustDecision {
+    public_key: String,
+    trusted: bool,
+}
+
+type TrustDecisions = BTreeMap<String, TrustDecision>;
+
+/// Select the configured substituters the user trusts
+///
+/// Binary caches can be added through the `nix.substituters` config,
+/// mapping substituter URLs to their public key.
+/// Since config may come from a project's `.flox/config.toml`,
+/// the user is asked once whether to trust each substituter (and key).
+/// The decision is stored in `trust_file`, see [substituters_trust_file],
+/// so that config can not supply its own decisions.
+/// Substituters that could smuggle additional nix settings are skipped.
+pub async fn init_substituters(
+    config_substituters: &HashMap<String, String>,
+    trust_file: &Path,
+) -> Result<Vec<(String, String)>> {
+    let mut decisions = read_decisions(trust_file)?;
+    let mut changed = false;
+
+    let mut candidates = config_substituters.iter().collect::<Vec<_>>();
+    candidates.sort();
+
+    for (url, public_key) in candidates {
+        if let Err(reason) = check_substituter(url, public_key) {
+            warn!("Ignoring substituter {url:?}: {reason}");
+            continue;
+        }
+
+        if decisions
+            .get(url)
+            .map_or(false, |decision| &decision.public_key == public_key)
+        {
+            continue;
+        }
+
+        if !Dialog::can_prompt() {
+            warn!("Ignoring substituter {url} until it is trusted in an interactive flox session");
+            continue;
+        }
+
+        let message = format!("Do you trust the binary cache {url}?");
+        let help = formatdoc! {"
+            nix will download prebuilt packages from {url}
+            and accept packages signed by {public_key}.
+            Only trust binary caches of people and organizations you trust."};
+
+        let dialog = Dialog {
+            message: &message,
+            help_message: Some(&help),
+            typed: Confirm {
+                default: Some(false),
+            },
+        };
+
+        let trusted = dialog.prompt().await?;
+        decisions.insert(url.clone(), TrustDecision {
+            public_key: public_key.clone(),
+            trusted,
+        });
+        changed = true;
+    }
+
+    if changed {
+        if let Some(parent) = trust_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file =
+            File::create(trust_file).with_context(|| format!("Could not write {trust_file:?}"))?;
+        serde_json::to_writer_pretty(file, &decisions)?;
+    }
+
+    Ok(trusted_substituters(config_substituters, &decisions))
+}
+
+/// The configured substituters the user already trusts, without prompting
+pub fn read_substituters(
+    config_substituters: &HashMap<String, String>,
+    trust_file: &Path,
+) -> Result<Vec<(String, String)>> {
+    let decisions = read_decisions(trust_file)?;
+    Ok(trusted_substituters(config_substituters, &decisions))
+}
+
+/// The file in the default data dir recording the trust decisions
+pub fn substituters_trust_file() -> Result<PathBuf> {
+    Ok(xdg_data_dir()?.join(SUBSTITUTERS_TRUST_FILE_NAME))
+}
+
+/// Substituters end up in `NIX_CONFIG`,
+/// where whitespace would add further substituters or keys
+/// and newlines would add arbitrary settings.
+fn check_substituter(url: &str, public_key: &str) -> Result<(), String> {
+    let invalid_char = |c: char| c.is_whitespace() || c.is_control();
+    if url.contains(invalid_char) {
+        return Err("the URL contains whitespace or control characters".to_string());
+    }
+    if public_key.contains(invalid_char) {
+        return Err("the public key contains whitespace or control characters".to_string());
+    }
+    Url::parse(url).map_err(|err| format!("invalid URL: {err}"))?;
+    Ok(())
+}
+
+fn read_decisions(trust_file: &Path) -> Result<TrustDecisions> {
+    if !trust_file.exists() {
+        debug!("No substituters have been trusted yet");
+        return Ok(Default::default());
+    }
+
+    serde_json::from_reader(File::open(trust_file)?)
+        .with_context(|| format!("Could not read {trust_file:?}"))
+}
+
+fn trusted_substituters(
+    config_substituters: &HashMap<String, String>,
+    decisions: &TrustDecisions,
+) -> Vec<(String, String)> {
+    let mut substituters = config_substituters
+        .iter()
+        .filter(|(url, public_key)| check_substituter(url, public_key).is_ok())
+        .filter(|(url, public_key)| {
+            decisions.get(*url).map_or(false, |decision| {
+                decision.trusted && &decision.public_key == *public_key
+            })
+        })
+        .map(|(url, public_key)| (url.clone(), public_key.clone()))
+        .collect::<Vec<_>>();
+    substituters.sort();
+    substituters
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::config::{build_config, Config};
+
+    fn trust(trust_file: &Path, substituters: &[(&str, &str)]) {
+        let decisions = substituters
+            .iter()
+            .map(|(url, public_key)| {
+                (url.to_string(), TrustDecision {
+                    public_key: public_key.to_string(),
+                    trusted: true,
+                })
+            })
+            .collect::<TrustDecisions>();
+        fs::write(trust_file, serde_json::to_string(&decisions).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn project_config_can_not_pretrust_substituters() {
+        let data_home = tempfile::tempdir().unwrap();
+        let trust_file = data_home.path().join(SUBSTITUTERS_TRUST_FILE_NAME);
+
+        let project = tempfile::tempdir().unwrap();
+        let project_data_dir = project.path().join(".flox/data");
+        fs::create_dir_all(&project_data_dir).unwrap();
+        fs::write(project.path().join(".flox/config.toml"), formatdoc! {r#"
+                data_dir = "{}"
+
+                [nix.substituters]
+                "https://cache.example.com" = "cache.example.com-1:key"
+            "#, project_data_dir.display()})
+        .unwrap();
+
+        let substituters = [("https://cache.example.com", "cache.example.com-1:key")];
+        trust(
+            &project_data_dir.join(SUBSTITUTERS_TRUST_FILE_NAME),
+            &substituters,
+        );
+
+        let config: Config = build_config(
+            Path::new("/cache"),
+            Path::new("/data"),
+            &project.path().join("config"),
+            Some(&project.path().join(".flox/config.toml")),
+            Vec::new(),
+        )
+        .unwrap()
+        .try_deserialize()
+        .unwrap();
+
+        assert_eq!(config.nix.substituters.len(), 1);
+        assert_eq!(
+            read_substituters(&config.nix.substituters, &trust_file).unwrap(),
+            vec![]
+        );
+
+        // decisions in the default data dir are respected
+        trust(&trust_file, &substituters);
+        assert_eq!(
+            read_substituters(&config.nix.substituters, &trust_file).unwrap(),
+            vec![(
+                "https://cache.example.com".to_string(),
+                "cache.example.com-1:key".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_substituters_injecting_nix_config() {
+        let data_home = tempfile::tempdir().unwrap();
+        let trust_file = data_home.path().join(SUBSTITUTERS_TRUST_FILE_NAME);
+
+        let substituters = [
+            ("https://cache.example.com", "cache.example.com-1:key"),
+            (
+                "https://a.example.com\nsandbox = false",
+                "a.example.com-1:key",
+            ),
+            (
+                "https://b.example.com https://c.example.com",
+                "b.example.com-1:key",
+            ),
+            ("https://d.example.com", "d.example.com-1:key other-1:key"),
+            ("not a url", "e.example.com-1:key"),
+            ("not-a-url", "f.example.com-1:key"),
+        ];
+        trust(&trust_file, &substituters);
+        let config_substituters = substituters
+            .iter()
+            .map(|(url, public_key)| (url.to_string(), public_key.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let expected = vec![(
+            "https://cache.example.com".to_string(),
+            "cache.example.com-1:key".to_string(),
+        )];
+        assert_eq!(
+            init_substituters(&config_substituters, &trust_file)
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            read_substituters(&config_substituters, &trust_file).unwrap(),
+            expected
+        );
+    }
+}
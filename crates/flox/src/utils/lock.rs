@@ -0,0 +1,190 @@
+//! Advisory file locks that record their owner
+//!
+//! Wraps [fslock::LockFile], writing the PID and acquisition time of the
+//! owning process into the lock file, and clearing it again on release.
+//! If a lock can not be acquired in time, the error names the process holding it.
+//! Locks are released by the OS when their owner exits,
+//! so locks of processes that crashed never block other processes.
+//! Their owner record is left behind though, which is logged by the next owner.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use fslock::LockFile;
+use log::debug;
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use time::OffsetDateTime;
+
+/// How long to wait for a lock held by another process
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+    // unlocks on drop, after the owner record was cleared
+    _file: LockFile,
+}
+
+/// The process that acquired a lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOwner {
+    pub pid: i32,
+    /// Unix timestamp of the lock acquisition
+    pub acquired: i64,
+}
+
+impl Lock {
+    /// Acquire the lock at `path`, waiting at most [LOCK_TIMEOUT]
+    pub async fn acquire(path: impl AsRef<Path>) -> Result<Lock> {
+        Self::acquire_timeout(path, LOCK_TIMEOUT).await
+    }
+
+    /// Acquire the lock at `path`, waiting at most `timeout`
+    pub async fn acquire_timeout(path: impl AsRef<Path>, timeout: Duration) -> Result<Lock> {
+        let path = path.as_ref();
+        let mut file =
+            LockFile::open(path).with_context(|| format!("Could not open lock file {path:?}"))?;
+
+        let deadline = Instant::now() + timeout;
+        while !file
+            .try_lock()
+            .with_context(|| format!("Could not lock {path:?}"))?
+        {
+            if Instant::now() >= deadline {
+                match LockOwner::read(path).filter(LockOwner::is_running) {
+                    Some(owner) => bail!("Timed out waiting for lock {path:?}, {owner}"),
+                    None => bail!("Timed out waiting for lock {path:?} held by another process"),
+                }
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+
+        if let Some(previous) = LockOwner::read(path) {
+            if !previous.is_running() {
+                debug!(
+                    "Lock {path:?} was last held by process {pid}, which did not release it",
+                    pid = previous.pid
+                );
+            }
+        }
+
+        fs::write(path, LockOwner::current().serialize())
+            .with_context(|| format!("Could not record owner of lock {path:?}"))?;
+
+        debug!("Acquired lock {path:?}");
+        Ok(Lock {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // clear the owner record while still holding the lock
+        if let Err(err) = fs::write(&self.path, "") {
+            debug!("Could not clear owner of lock {:?}: {err}", self.path);
+        }
+    }
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        LockOwner {
+            pid: std::process::id() as i32,
+            acquired: OffsetDateTime::now_utc().unix_timestamp(),
+        }
+    }
+
+    /// Read the owner recorded in the lock file at `path`
+    ///
+    /// Returns [None] if the file is empty, i.e. the lock was released,
+    /// or was written by a process that does not record its owner.
+    /// An owner that is not running anymore exited without releasing the lock.
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::parse(&fs::read_to_string(path).ok()?)
+    }
+
+    fn serialize(&self) -> String {
+        format!("{} {}\n", self.pid, self.acquired)
+    }
+
+    fn parse(content: &str) -> Option<Self> {
+        let mut fields = content.split_whitespace();
+        let pid = fields.next()?.parse().ok()?;
+        let acquired = fields.next().and_then(|t| t.parse().ok()).unwrap_or(0);
+        Some(LockOwner { pid, acquired })
+    }
+
+    /// Whether the owning process is still running
+    pub fn is_running(&self) -> bool {
+        !matches!(kill(Pid::from_raw(self.pid), None), Err(Errno::ESRCH))
+    }
+}
+
+impl Display for LockOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let acquired = OffsetDateTime::from_unix_timestamp(self.acquired)
+            .map(|time| time.to_string())
+            .unwrap_or_else(|_| "an unknown time".to_string());
+        write!(f, "held by process {} since {acquired}", self.pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_roundtrip() {
+        let owner = LockOwner {
+            pid: 1234,
+            acquired: 1700000000,
+        };
+        assert_eq!(LockOwner::parse(&owner.serialize()), Some(owner));
+        assert_eq!(
+            LockOwner::parse("1234"),
+            Some(LockOwner {
+                pid: 1234,
+                acquired: 0
+            })
+        );
+        assert_eq!(LockOwner::parse(""), None);
+    }
+
+    #[tokio::test]
+    async fn records_and_clears_owner() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("lock");
+
+        let lock = Lock::acquire(&path).await.unwrap();
+        let owner = LockOwner::read(&path).unwrap();
+        assert_eq!(owner.pid, std::process::id() as i32);
+        assert!(owner.is_running());
+
+        drop(lock);
+        assert_eq!(LockOwner::read(&path), None);
+    }
+
+    #[tokio::test]
+    async fn timeout_names_owner() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = tempdir.path().join("lock");
+
+        let _lock = Lock::acquire(&path).await.unwrap();
+        let err = Lock::acquire_timeout(&path, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("held by process {}", std::process::id())));
+    }
+}
@@ -1,7 +1,7 @@
+use std::path::Path;
 use std::sync::mpsc;
 
 use anyhow::Result;
-use fslock::LockFile;
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -12,6 +12,7 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use uuid::Uuid;
 
 use crate::config::Config;
+use crate::utils::lock::Lock;
 
 pub const FLOX_VERSION: &str = env!("FLOX_VERSION");
 
@@ -210,6 +211,20 @@ async fn push_metrics(metrics: Vec<MetricEntry>, uuid: Uuid) -> Result<()> {
     Ok(())
 }
 
+/// Acquire the lock guarding the metrics files in `cache_dir`
+///
+/// Metrics are best effort,
+/// if the lock can not be acquired metrics are skipped instead of failing the command.
+pub async fn metrics_lock(cache_dir: &Path) -> Option<Lock> {
+    match Lock::acquire(cache_dir.join(METRICS_LOCK_FILE_NAME)).await {
+        Ok(lock) => Some(lock),
+        Err(err) => {
+            debug!("Skipping metrics: {err:#}");
+            None
+        },
+    }
+}
+
 pub async fn add_metric(subcommand: Option<String>) -> Result<()> {
     let config = Config::parse()?;
 
@@ -220,8 +235,10 @@ pub async fn add_metric(subcommand: Option<String>) -> Result<()> {
     let cache_dir = config.flox.cache_dir;
     let data_dir = config.flox.data_dir;
 
-    let mut metrics_lock = LockFile::open(&cache_dir.join(METRICS_LOCK_FILE_NAME))?;
-    tokio::task::spawn_blocking(move || metrics_lock.lock()).await??;
+    let lock = match metrics_lock(&cache_dir).await {
+        Some(lock) => lock,
+        None => return Ok(()),
+    };
 
     let uuid_path = data_dir.join(METRICS_UUID_FILE_NAME);
 
@@ -252,31 +269,52 @@ pub async fn add_metric(subcommand: Option<String>) -> Result<()> {
         .write(true)
         .read(true)
         .create(true)
-        .open(buffer_file_path)
+        .truncate(false)
+        .open(&buffer_file_path)
         .await?;
 
     let mut buffer_json = String::new();
     events_buffer_file.read_to_string(&mut buffer_json).await?;
-    let mut buffer_iter = serde_json::Deserializer::from_str(&buffer_json)
+    let mut buffer: Vec<MetricEntry> = serde_json::Deserializer::from_str(&buffer_json)
         .into_iter::<MetricEntry>()
         .filter_map(|x| x.ok())
-        .peekable();
+        .collect();
 
     let now = OffsetDateTime::now_utc();
 
     let new_entry = MetricEntry::new(subcommand, now);
 
-    // Note: assumes the oldest metric entry must come first
-    if buffer_iter
-        .peek()
-        .map(|e| (now - e.timestamp) > Duration::hours(2))
+    // Entries re-buffered after a failed push may follow newer ones,
+    // so look at the oldest timestamp rather than the first entry
+    if buffer
+        .iter()
+        .map(|e| e.timestamp)
+        .min()
+        .map(|oldest| (now - oldest) > Duration::hours(2))
         .unwrap_or(false)
     {
         debug!("Pushing buffered metrics");
-        let mut buffer: Vec<MetricEntry> = buffer_iter.collect();
+        let pending = format!("{buffer_json}\n{}", serde_json::to_string(&new_entry)?);
         buffer.push(new_entry);
-        push_metrics(buffer, uuid).await?;
+
+        // don't block other flox processes while pushing,
+        // the entries are buffered again if the push fails
         events_buffer_file.set_len(0).await?;
+        drop(events_buffer_file);
+        drop(lock);
+
+        if let Err(err) = push_metrics(buffer, uuid).await {
+            if let Some(_metrics_lock) = metrics_lock(&cache_dir).await {
+                OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(&buffer_file_path)
+                    .await?
+                    .write_all(pending.as_bytes())
+                    .await?;
+            }
+            return Err(err);
+        }
     } else {
         debug!("Writing new metrics buffer entry");
         events_buffer_file
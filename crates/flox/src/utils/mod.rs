@@ -20,8 +20,10 @@ mod completion;
 pub mod dialog;
 pub mod init;
 pub mod installables;
+pub mod lock;
 pub mod logger;
 pub mod metrics;
+pub mod plugins;
 
 use regex::Regex;
 use tokio::sync::Mutex;
@@ -206,6 +208,26 @@ impl<Matching: InstallableDef + 'static> InstallableArgument<Unparsed, Matching>
         comp.into_iter().map(|a| (a, None)).collect()
     }
 }
+
+/// Completion function for environment arguments in the bpaf completion engine
+pub fn complete_environment<P: AsRef<Path>>(environment: &P) -> Vec<(String, Option<String>)> {
+    let environment = environment.as_ref().to_string_lossy().into_owned();
+
+    let flox = Flox::completion_instance().expect("Could not initialize flox instance");
+
+    let handle = tokio::runtime::Handle::current();
+    let comp = std::thread::spawn(move || {
+        handle
+            .block_on(flox.complete_environment(&environment))
+            .map_err(|e| debug!("Failed to complete environment: {e}"))
+            .unwrap_or_default()
+    })
+    .join()
+    .unwrap();
+
+    comp.into_iter().map(|a| (a, None)).collect()
+}
+
 impl<Matching: InstallableDef + 'static> InstallableArgument<Parsed, Matching> {
     async fn resolve_matches(&self, flox: &Flox) -> Result<Vec<ResolvedInstallableMatch>> {
         let drv = InstallableKind::any(Matching::DERIVATION_TYPES).unwrap();
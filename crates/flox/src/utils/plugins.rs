@@ -0,0 +1,296 @@
+//! Discovery and invocation of external `flox-<name>` subcommands
+//!
+//! Similar to `git`, any executable named `flox-<name>` found on `$PATH`
+//! can be invoked as `flox <name> [args...]`.
+//! Built-in commands always take precedence over plugins.
+//!
+//! Plugins parse their own arguments, including any `--environment` selection.
+//! Activation state is part of the environment inherited from the calling shell.
+
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::debug;
+use tokio::process::Command;
+
+use crate::commands::flox_args;
+use crate::config::Config;
+
+/// Prefix of executables considered flox plugins
+pub const PLUGIN_PREFIX: &str = "flox-";
+
+/// Path to the `flox` executable invoking the plugin
+pub const ENV_PLUGIN_FLOX_BIN: &str = "FLOX_BIN";
+/// Name of the invoked plugin (without [PLUGIN_PREFIX])
+pub const ENV_PLUGIN_NAME: &str = "FLOX_PLUGIN_NAME";
+/// The system flox runs on, e.g. `x86_64-linux`
+pub const ENV_PLUGIN_SYSTEM: &str = "FLOX_SYSTEM";
+/// flox' cache directory
+pub const ENV_PLUGIN_CACHE_DIR: &str = "FLOX_CACHE_DIR";
+/// flox' data directory, containing environments
+pub const ENV_PLUGIN_DATA_DIR: &str = "FLOX_DATA_DIR";
+/// flox' config directory
+pub const ENV_PLUGIN_CONFIG_DIR: &str = "FLOX_CONFIG_HOME";
+
+/// General options preceding a subcommand that take a separate value
+const OPTIONS_WITH_VALUE: &[&str] = &["--stability"];
+
+/// An executable providing the `flox <name>` subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Plugin {
+    /// List all plugins found on `$PATH`
+    ///
+    /// Plugins found in earlier `$PATH` entries shadow plugins of the same name
+    /// found later, i.e. every name is listed at most once.
+    pub fn discover() -> Vec<Plugin> {
+        Self::discover_in(&search_path())
+    }
+
+    fn discover_in(search_path: &OsStr) -> Vec<Plugin> {
+        let mut seen = HashSet::new();
+        Self::candidates_in(search_path)
+            .into_iter()
+            .filter(|plugin| plugin.is_executable())
+            .filter(|plugin| seen.insert(plugin.name.clone()))
+            .collect()
+    }
+
+    /// Find the plugin providing `flox <name>`
+    pub fn find(name: &str) -> Option<Plugin> {
+        Self::find_in(name, &search_path())
+    }
+
+    fn find_in(name: &str, search_path: &OsStr) -> Option<Plugin> {
+        Self::discover_in(search_path)
+            .into_iter()
+            .find(|plugin| plugin.name == name)
+    }
+
+    /// Find a plugin for the first positional argument in `args`
+    ///
+    /// Returns the plugin along with the arguments that follow its name.
+    /// Returns [None] if the name refers to a built-in command.
+    pub fn from_args(args: &[String]) -> Option<(Plugin, Vec<String>)> {
+        Self::from_args_in(args, &search_path())
+    }
+
+    fn from_args_in(args: &[String], search_path: &OsStr) -> Option<(Plugin, Vec<String>)> {
+        let position = name_position(args)?;
+        let name = &args[position];
+
+        if is_builtin(name) {
+            return None;
+        }
+
+        let plugin = Self::find_in(name, search_path)?;
+        Some((plugin, args[position + 1..].to_vec()))
+    }
+
+    /// All files on `$PATH` that look like plugins, including shadowed and
+    /// non-executable ones, in `$PATH` order
+    pub fn candidates() -> Vec<Plugin> {
+        Self::candidates_in(&search_path())
+    }
+
+    fn candidates_in(search_path: &OsStr) -> Vec<Plugin> {
+        env::split_paths(search_path)
+            .filter_map(|dir| dir.read_dir().ok())
+            .flat_map(|entries| {
+                let mut plugins = entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let file_name = entry.file_name();
+                        let name = file_name.to_str()?.strip_prefix(PLUGIN_PREFIX)?;
+                        if name.is_empty() {
+                            return None;
+                        }
+                        Some(Plugin {
+                            name: name.to_string(),
+                            path: entry.path(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                plugins.sort_by(|a, b| a.name.cmp(&b.name));
+                plugins
+            })
+            .collect()
+    }
+
+    /// The plugins section appended to `flox --help`
+    ///
+    /// Returns [None] if no usable plugins are installed.
+    pub fn help_section() -> Option<String> {
+        let plugins = Self::discover()
+            .into_iter()
+            .filter(|plugin| !plugin.is_shadowed_by_builtin())
+            .collect::<Vec<_>>();
+
+        if plugins.is_empty() {
+            return None;
+        }
+
+        let width = plugins.iter().map(|plugin| plugin.name.len()).max()?;
+        let mut section = "\nPlugins:\n".to_string();
+        for plugin in plugins {
+            section.push_str(&format!(
+                "    {:width$}  {}\n",
+                plugin.name,
+                plugin.path.display()
+            ));
+        }
+        Some(section)
+    }
+
+    /// Whether the plugin refers to an executable file
+    pub fn is_executable(&self) -> bool {
+        is_executable(&self.path)
+    }
+
+    /// Whether the plugin is shadowed by a built-in command of the same name
+    pub fn is_shadowed_by_builtin(&self) -> bool {
+        is_builtin(&self.name)
+    }
+
+    /// Run the plugin with `args`, returning its exit code
+    ///
+    /// Besides the environment of the calling process
+    /// (including variables set by an active environment),
+    /// plugins receive the context of the current flox invocation
+    /// through the `FLOX_*` variables defined in this module.
+    pub async fn run(&self, config: &Config, args: &[impl AsRef<OsStr> + Debug]) -> Result<u8> {
+        debug!("Running plugin {:?} with arguments: {:?}", self.path, args);
+
+        let flox_bin = env::current_exe().context("Could not determine flox executable")?;
+
+        let status = Command::new(&self.path)
+            .args(args)
+            .env(ENV_PLUGIN_FLOX_BIN, flox_bin)
+            .env(ENV_PLUGIN_NAME, &self.name)
+            .env(ENV_PLUGIN_SYSTEM, env!("NIX_TARGET_SYSTEM"))
+            .env(ENV_PLUGIN_CACHE_DIR, &config.flox.cache_dir)
+            .env(ENV_PLUGIN_DATA_DIR, &config.flox.data_dir)
+            .env(ENV_PLUGIN_CONFIG_DIR, &config.flox.config_dir)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {:?}", self.path))?
+            .wait()
+            .await
+            .with_context(|| format!("Failed running plugin {:?}", self.path))?;
+
+        let code = status.code().unwrap_or_else(|| {
+            status
+                .signal()
+                .expect("Process terminated by unknown means")
+        }) as u8;
+
+        Ok(code)
+    }
+}
+
+/// Whether `name` is a subcommand known to flox itself
+///
+/// Built-in commands print their help with `flox <name> --help`,
+/// unknown commands fail to parse.
+fn is_builtin(name: &str) -> bool {
+    let args: &[&str] = &[name, "--help"];
+    matches!(
+        flox_args().run_inner(args.into()),
+        Err(bpaf::ParseFailure::Stdout(_))
+    )
+}
+
+/// Position of the first argument that is neither an option nor an option's value
+fn name_position(args: &[String]) -> Option<usize> {
+    let mut skip_value = false;
+    args.iter().position(|arg| {
+        if skip_value {
+            skip_value = false;
+            return false;
+        }
+        skip_value = OPTIONS_WITH_VALUE.contains(&arg.as_str());
+        !arg.starts_with('-')
+    })
+}
+
+fn search_path() -> std::ffi::OsString {
+    env::var_os("PATH").unwrap_or_default()
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write_plugin(dir: &Path, name: &str, mode: u32) -> PathBuf {
+        let path = dir.join(format!("{PLUGIN_PREFIX}{name}"));
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn discovers_plugins_on_search_path() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+
+        let hello = write_plugin(first.path(), "hello", 0o755);
+        write_plugin(first.path(), "not-executable", 0o644);
+        write_plugin(second.path(), "hello", 0o755);
+        let builtin = write_plugin(second.path(), "search", 0o755);
+
+        let search_path = env::join_paths([first.path(), second.path()]).unwrap();
+
+        assert_eq!(Plugin::candidates_in(&search_path).len(), 4);
+        assert_eq!(Plugin::discover_in(&search_path), vec![
+            Plugin {
+                name: "hello".to_string(),
+                path: hello.clone(),
+            },
+            Plugin {
+                name: "search".to_string(),
+                path: builtin,
+            },
+        ]);
+
+        let args = ["-v", "hello", "--flag", "value"].map(String::from);
+        assert_eq!(
+            Plugin::from_args_in(&args, &search_path),
+            Some((
+                Plugin {
+                    name: "hello".to_string(),
+                    path: hello,
+                },
+                vec!["--flag".to_string(), "value".to_string()]
+            ))
+        );
+
+        let args = ["--stability", "unstable", "hello"].map(String::from);
+        assert_eq!(
+            Plugin::from_args_in(&args, &search_path).map(|(plugin, _)| plugin.name),
+            Some("hello".to_string())
+        );
+
+        let args = ["not-executable".to_string()];
+        assert_eq!(Plugin::from_args_in(&args, &search_path), None);
+
+        let args = ["search".to_string()];
+        assert_eq!(Plugin::from_args_in(&args, &search_path), None);
+    }
+}